@@ -1,40 +1,258 @@
 use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::time::Duration;
 
 use toml::value::Datetime;
 
 use serde::Deserialize;
 use serde::Serialize;
 
+/// Serde (de)serialization for [`Duration`] fields, accepting either a
+/// human-readable string (`"30s"`, `"5m"`, `"500ms"`) or a plain number of
+/// seconds for backward compatibility with bare-number config values.
+pub mod duration_format {
+    use std::fmt;
+    use std::time::Duration;
+
+    use serde::de::Error as DeError;
+    use serde::de::Visitor;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&format!("{}ms", duration.as_millis()))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DurationVisitor)
+    }
+
+    struct DurationVisitor;
+    impl<'de> Visitor<'de> for DurationVisitor {
+        type Value = Duration;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(
+                "a duration string like \"30s\", \"5m\", \"500ms\", or a plain number of seconds",
+            )
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Duration, E>
+        where
+            E: DeError,
+        {
+            parse(value).map_err(DeError::custom)
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<Duration, E>
+        where
+            E: DeError,
+        {
+            seconds(value).map_err(DeError::custom)
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<Duration, E>
+        where
+            E: DeError,
+        {
+            seconds(value as f64).map_err(DeError::custom)
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Duration, E>
+        where
+            E: DeError,
+        {
+            seconds(value as f64).map_err(DeError::custom)
+        }
+    }
+
+    /// Build a [`Duration`] from a number of seconds, rejecting values
+    /// `Duration::from_secs_f64` would otherwise panic on (negative, NaN, or
+    /// infinite).
+    fn seconds(value: f64) -> Result<Duration, String> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!(
+                "invalid duration: {value} seconds is not a finite, non-negative number"
+            ));
+        }
+        Ok(Duration::from_secs_f64(value))
+    }
+
+    fn parse(s: &str) -> Result<Duration, String> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(|| format!("invalid duration {s:?}: missing unit (ms, s, or m)"))?;
+        let (number, unit) = s.split_at(split);
+        let value: f64 = number
+            .parse()
+            .map_err(|_| format!("invalid duration {s:?}: {number:?} is not a number"))?;
+        let seconds_value = match unit {
+            "ms" => value / 1_000.0,
+            "s" => value,
+            "m" => value * 60.0,
+            _ => return Err(format!("invalid duration {s:?}: unknown unit {unit:?}")),
+        };
+        seconds(seconds_value).map_err(|_| format!("invalid duration {s:?}: out of range"))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parses_units() {
+            assert_eq!(parse("30s").unwrap(), Duration::from_secs(30));
+            assert_eq!(parse("5m").unwrap(), Duration::from_secs(300));
+            assert_eq!(parse("500ms").unwrap(), Duration::from_millis(500));
+        }
+
+        #[test]
+        fn rejects_negative() {
+            assert!(parse("-1s").is_err());
+            assert!(seconds(-5.0).is_err());
+        }
+
+        #[test]
+        fn rejects_overflowing_exponent() {
+            assert!(parse("1e400s").is_err());
+        }
+
+        #[test]
+        fn rejects_unknown_unit() {
+            assert!(parse("30x").is_err());
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct Config {
     pub timing: Timing,
+    #[serde(default)]
+    pub logging: Logging,
+    /// Optional embedded HTTP status server.
+    pub http: Option<Http>,
+    #[serde(default)]
+    pub failures: Failures,
     pub pumps: BTreeMap<String, Pump>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Failures {
+    /// Default number of consecutive failures after which a pump is
+    /// disabled, unless overridden per-pump.
+    pub max_consecutive_failures: u32,
+}
+impl Default for Failures {
+    fn default() -> Self {
+        Failures {
+            max_consecutive_failures: 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Http {
+    /// Address, e.g. `127.0.0.1:8080`, the status server binds to.
+    pub bind_address: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Logging {
+    /// Maximum size of the log file before it is rotated.
+    pub max_log_bytes: u64,
+    /// Number of rotated backups to keep.
+    pub log_backups: u32,
+}
+impl Default for Logging {
+    fn default() -> Self {
+        Logging {
+            max_log_bytes: 4 * 1024 * 1024,
+            log_backups: 5,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Timing {
     /// Time when the daily watering is started.
     pub daily_start_time: Datetime,
+    /// Pause between running successive pumps in a watering cycle.
+    #[serde(with = "duration_format", default = "default_pause_between_pumps")]
+    pub pause_between_pumps: Duration,
+    /// Maximal error of the scheduler's once-a-second sleep before it's
+    /// treated as a system clock jump, restarting the wait for the next
+    /// watering time.
+    #[serde(with = "duration_format", default = "default_time_jump_tolerance")]
+    pub time_jump_tolerance: Duration,
 }
 impl Default for Timing {
     fn default() -> Self {
         Timing {
             daily_start_time: Datetime::from_str("07:30:00").unwrap(),
+            pause_between_pumps: default_pause_between_pumps(),
+            time_jump_tolerance: default_time_jump_tolerance(),
         }
     }
 }
 
+fn default_pause_between_pumps() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_time_jump_tolerance() -> Duration {
+    Duration::from_secs(10)
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
 pub struct Pump {
-    /// Name of the connector
-    pub connector: String,
+    /// Name of the GPIO line to request, matched as a prefix against the
+    /// names reported by `gpio_cdev`.
+    pub pin_name: String,
     /// Name of the device, typically `/dec/gpiochipN`.
     pub device: String,
     /// Pin offset within the device.
     pub offset: u32,
+    /// Whether the pin is actually requested as an output. A pump configured
+    /// with `enable = false` is reported but never driven.
+    #[serde(default = "default_enable")]
+    pub enable: bool,
     /// Amount of water pumped per second.
     pub ml_per_s: f64,
     /// Amount of water required per day.
     pub ml_per_day: f64,
+    /// Moisture sensor used to decide whether watering is needed.
+    ///
+    /// Pumps without a sensor keep the time-based watering schedule.
+    pub sensor: Option<Sensor>,
+    /// Overrides `failures.max_consecutive_failures` for this pump.
+    pub max_consecutive_failures: Option<u32>,
+    /// Longest single pump run, e.g. `"30s"`. Replaces the previously
+    /// hard-coded 30 s safety clamp; raise it for larger reservoirs.
+    #[serde(with = "duration_format", default = "default_max_run_time")]
+    pub max_run_time: Duration,
+}
+
+fn default_enable() -> bool {
+    true
+}
+
+fn default_max_run_time() -> Duration {
+    Duration::from_secs(30)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Sensor {
+    /// Path to the 1-wire/ADC device file providing the raw reading.
+    pub device: String,
+    /// Reading at or below which the substrate counts as dry.
+    pub dry_threshold: f64,
+    /// Reading at or above which the substrate counts as wet.
+    pub wet_threshold: f64,
 }
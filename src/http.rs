@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use time::format_description;
+
+use tiny_http::Header;
+use tiny_http::Method;
+use tiny_http::Request;
+use tiny_http::Response;
+use tiny_http::Server;
+
+use log::error;
+use log::info;
+
+use crate::PumpNotFoundError;
+use crate::Shared;
+use crate::DEFAULT_TEST_SECS;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Serialize)]
+struct PumpStatus {
+    name: String,
+    ml_per_day: f64,
+    ml_per_s: f64,
+    enabled: bool,
+    failed: bool,
+    /// Pin location, formatted like `Pin`'s `Display` impl.
+    location: String,
+}
+
+#[derive(Serialize)]
+struct LastRunStatus {
+    at: String,
+    ok: bool,
+}
+
+#[derive(Serialize)]
+struct Status {
+    pumps: Vec<PumpStatus>,
+    watering_time: String,
+    next_watering: String,
+    last_run: Option<LastRunStatus>,
+}
+
+/// Serve the JSON status page and watering endpoints on `bind_address` until
+/// the process exits.
+pub fn serve(bind_address: String, shared: Arc<Shared>) {
+    let server = match Server::http(&bind_address) {
+        Ok(server) => server,
+        Err(err) => {
+            error!("failed to start http server on {bind_address}: {err}");
+            return;
+        }
+    };
+    info!("http status server listening on {bind_address}");
+    for request in server.incoming_requests() {
+        if let Err(err) = handle(request, &shared) {
+            error!("http request failed with error {err:?}");
+        }
+    }
+}
+
+fn handle(mut request: Request, shared: &Shared) -> Result<()> {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((url.as_str(), ""));
+
+    let response = if method == Method::Get && path == "/status" {
+        let status = build_status(shared)?;
+        let body = serde_json::to_string(&status)?;
+        let header: Header = "Content-Type: application/json"
+            .parse()
+            .map_err(|_| "invalid content-type header")?;
+        Response::from_string(body).with_header(header).boxed()
+    } else if method == Method::Post && path == "/water" {
+        shared.water_all();
+        Response::from_string("ok").boxed()
+    } else if let Some(name) = path
+        .strip_prefix("/pumps/")
+        .and_then(|rest| rest.strip_suffix("/water"))
+        .filter(|name| method == Method::Post && !name.is_empty())
+    {
+        let secs = query_param(query, "secs").unwrap_or(DEFAULT_TEST_SECS);
+        match water_one(shared, name, secs) {
+            Ok(()) => Response::from_string("ok").boxed(),
+            Err(err) => Response::from_string(err.to_string())
+                .with_status_code(404)
+                .boxed(),
+        }
+    } else {
+        Response::from_string("not found")
+            .with_status_code(404)
+            .boxed()
+    };
+
+    request.respond(response)?;
+    Ok(())
+}
+
+fn query_param(query: &str, key: &str) -> Option<f64> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, v)| v.parse().ok())
+}
+
+fn water_one(shared: &Shared, name: &str, secs: f64) -> Result<()> {
+    let mut pumps = shared.pumps.lock().unwrap();
+    match pumps.iter_mut().find(|pump| pump.name == name) {
+        Some(pump) => {
+            if !pump.accepts_duration(secs) {
+                return Err(format!(
+                    "requested duration {secs:.1}s out of range for pump {name:?}"
+                )
+                .into());
+            }
+            pump.pump_for_secs(secs)?;
+            // A manual test run that succeeds clears a disabled pump.
+            pump.clear_failure();
+            Ok(())
+        }
+        None => Err(Box::new(PumpNotFoundError {
+            pump_name: name.to_string(),
+        })),
+    }
+}
+
+fn build_status(shared: &Shared) -> Result<Status> {
+    let datetime_format = format_description::parse(
+        "[year]-[month]-[day] \
+         [hour]:[minute]:[second] \
+         [offset_hour sign:mandatory]:[offset_minute]",
+    )?;
+    let time_format = format_description::parse("[hour]:[minute]:[second]")?;
+
+    let pumps = shared
+        .pumps
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|pump| PumpStatus {
+            name: pump.name.clone(),
+            ml_per_day: pump.ml_per_day,
+            ml_per_s: pump.ml_per_s,
+            enabled: pump.pin.is_enabled(),
+            failed: pump.failed,
+            location: pump.pin.to_string(),
+        })
+        .collect();
+
+    let watering_time = *shared.watering_time.lock().unwrap();
+    let last_run = shared
+        .last_run
+        .lock()
+        .unwrap()
+        .clone()
+        .map(|last_run| -> Result<LastRunStatus> {
+            Ok(LastRunStatus {
+                at: last_run.at.format(&datetime_format)?,
+                ok: last_run.ok,
+            })
+        })
+        .transpose()?;
+
+    Ok(Status {
+        pumps,
+        watering_time: watering_time.format(&time_format)?,
+        next_watering: shared.next_watering().format(&datetime_format)?,
+        last_run,
+    })
+}
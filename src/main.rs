@@ -1,10 +1,15 @@
 use core::fmt;
 use core::time::Duration;
 
+use std::collections::BTreeMap;
+use std::fs;
 use std::fs::File;
-use std::fs::OpenOptions;
 use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::thread;
+use std::time::SystemTime;
 
 use time::ext::NumericalDuration;
 use time::format_description;
@@ -32,17 +37,30 @@ use clap::Parser;
 use clap::Subcommand;
 
 mod config;
+mod http;
+mod rotation;
+mod sensor;
+mod state;
+
+use rotation::RotatingWriter;
+use sensor::FileSensor;
+use sensor::Sensor;
 
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
 const DEFAULT_CONFIG_FILE_NAME: &str = "config.toml";
 const DEFAULT_LOG_FILE_NAME: &str = "water.log";
+const DEFAULT_STATE_FILE_NAME: &str = "water.state.json";
 /// Run a pump test for one second by default.
 const DEFAULT_TEST_SECS: f64 = 1.0;
 
 const CONSUMER: &str = "water";
 
+/// Pause between bursts so the substrate can absorb water before re-reading
+/// the sensor.
+const BURST_PAUSE: Duration = Duration::from_millis(1_000);
+
 #[derive(Debug)]
 struct Pin {
     /// The name of the pin.
@@ -99,6 +117,16 @@ impl fmt::Display for Pin {
     }
 }
 
+/// A moisture sensor attached to a pump, with the thresholds used to decide
+/// whether watering is needed.
+struct PumpSensor {
+    sensor: Box<dyn Sensor>,
+    /// Reading at or below which the substrate counts as dry.
+    dry_threshold: f64,
+    /// Reading at or above which the substrate counts as wet.
+    wet_threshold: f64,
+}
+
 struct Pump {
     /// The name of the pump.
     name: String,
@@ -108,52 +136,230 @@ struct Pump {
     ml_per_s: f64,
     /// Amount of water required per day.
     ml_per_day: f64,
+    /// Moisture sensor gating watering, if configured.
+    sensor: Option<PumpSensor>,
+    /// Number of watering attempts that failed in a row.
+    consecutive_failures: u32,
+    /// Number of consecutive failures after which the pump is disabled.
+    max_consecutive_failures: u32,
+    /// Set once `consecutive_failures` hits `max_consecutive_failures`;
+    /// excludes the pump from further cycles until cleared.
+    failed: bool,
+    /// Longest single pump run; replaces the previous hard-coded 30 s clamp.
+    max_run_time: Duration,
 }
 impl Pump {
-    fn new(name: &str, pin: Pin, ml_per_s: f64, ml_per_day: f64) -> Result<Pump> {
+    fn new(
+        name: &str,
+        pin: Pin,
+        ml_per_s: f64,
+        ml_per_day: f64,
+        sensor: Option<PumpSensor>,
+        max_consecutive_failures: u32,
+        max_run_time: Duration,
+    ) -> Result<Pump> {
         Ok(Pump {
             name: name.to_string(),
             pin,
             ml_per_s,
             ml_per_day,
+            sensor,
+            consecutive_failures: 0,
+            max_consecutive_failures,
+            failed: false,
+            max_run_time,
         })
     }
+    /// Reset the failure count and clear a `failed` pump, e.g. after a
+    /// successful manual test.
+    fn clear_failure(&mut self) {
+        self.consecutive_failures = 0;
+        self.failed = false;
+    }
     fn pump(&self, duration: Duration) -> Result<()> {
         self.pin.create_pulse(duration)?;
         Ok(())
     }
+    /// Whether `secs` is within this pump's configured `max_run_time`.
+    fn accepts_duration(&self, secs: f64) -> bool {
+        0.0 <= secs && secs <= self.max_run_time.as_secs_f64()
+    }
     fn pump_for_secs(&self, secs: f64) -> Result<()> {
         let name = &self.name;
-        if 0.0 <= secs && secs <= 30.0 {
+        if self.accepts_duration(secs) {
             // TODO use checked conversion when stabilized
             let duration = Duration::from_secs_f64(secs);
             self.pump(duration)?;
         } else {
-            warn!("{name}: pump duration {secs:.1}s out of range (min 0s, max 30s), doing nothing",);
+            let max_secs = self.max_run_time.as_secs_f64();
+            warn!(
+                "{name}: pump duration {secs:.1}s out of range (min 0s, max {max_secs:.1}s), doing nothing",
+            );
         }
         Ok(())
     }
-    fn water(&self) -> Result<()> {
+    /// Water the pump for today, returning the seconds and mL actually
+    /// dispensed (less than `ml_per_day` if a sensor cut the cycle short).
+    fn water(&self) -> Result<(f64, f64)> {
         let name = &self.name;
         let ml = self.ml_per_day;
         let ml_per_s = self.ml_per_s;
         let secs = ml / ml_per_s;
-        info!("{name}: pumping {ml:.0}mL in {secs:.1}s at {ml_per_s:.1}mL/s");
-        self.pump_for_secs(secs)?;
-        Ok(())
+
+        if let Some(pump_sensor) = &self.sensor {
+            let max_secs = self.max_run_time.as_secs_f64();
+            water_with_sensor(
+                name,
+                secs,
+                ml_per_s,
+                max_secs,
+                pump_sensor,
+                |burst| self.pump_for_secs(burst),
+                || thread::sleep(BURST_PAUSE),
+            )
+        } else {
+            info!("{name}: pumping {ml:.0}mL in {secs:.1}s at {ml_per_s:.1}mL/s");
+            self.pump_for_secs(secs)?;
+            if self.accepts_duration(secs) {
+                Ok((secs, ml))
+            } else {
+                // pump_for_secs did nothing for an out-of-range duration.
+                Ok((0.0, 0.0))
+            }
+        }
+    }
+}
+
+/// Run the sensor-gated burst loop: pump in bursts of up to `max_secs`,
+/// re-reading the sensor between bursts and stopping once its reading rises
+/// above `pump_sensor.dry_threshold` (no longer dry) or the full `secs`
+/// budget has been dispensed. Split out of [`Pump::water`] so the threshold
+/// logic can be unit-tested without a real GPIO pin.
+fn water_with_sensor(
+    name: &str,
+    secs: f64,
+    ml_per_s: f64,
+    max_secs: f64,
+    pump_sensor: &PumpSensor,
+    mut pump_for_secs: impl FnMut(f64) -> Result<()>,
+    mut sleep: impl FnMut(),
+) -> Result<(f64, f64)> {
+    let value = pump_sensor.sensor.read()?;
+    info!("{name}: moisture reading {value:.1}");
+    if value >= pump_sensor.wet_threshold {
+        info!("{name}: substrate is wet, skipping watering");
+        return Ok((0.0, 0.0));
+    }
+
+    info!("{name}: pumping in bursts of up to {max_secs:.1}s at {ml_per_s:.1}mL/s");
+    let mut remaining = secs;
+    let mut pumped_secs = 0.0;
+    loop {
+        let burst = remaining.min(max_secs);
+        pump_for_secs(burst)?;
+        pumped_secs += burst;
+        remaining -= burst;
+        if remaining <= 0.0 {
+            break;
+        }
+
+        sleep();
+        let value = pump_sensor.sensor.read()?;
+        info!("{name}: moisture reading {value:.1}");
+        if value > pump_sensor.dry_threshold {
+            break;
+        }
+    }
+    Ok((pumped_secs, pumped_secs * ml_per_s))
+}
+
+#[cfg(test)]
+mod pump_tests {
+    use super::*;
+    use std::cell::Cell;
+
+    struct FakeSensor {
+        readings: Vec<f64>,
+        next: Cell<usize>,
+    }
+    impl Sensor for FakeSensor {
+        fn read(&self) -> Result<f64> {
+            let i = self.next.get().min(self.readings.len() - 1);
+            self.next.set(i + 1);
+            Ok(self.readings[i])
+        }
+    }
+
+    #[test]
+    fn bursts_until_dry_threshold_is_cleared() {
+        // Full budget would take 5 bursts of 10s. The soil is still dry
+        // (<= dry_threshold) after the first burst, so a second burst runs;
+        // the reading after that clears dry_threshold, so watering should
+        // stop there instead of running all 5 bursts.
+        let pump_sensor = PumpSensor {
+            sensor: Box::new(FakeSensor {
+                readings: vec![1.0, 2.0, 5.0],
+                next: Cell::new(0),
+            }),
+            dry_threshold: 3.0,
+            wet_threshold: 9.0,
+        };
+        let mut bursts = Vec::new();
+        let (pumped_secs, pumped_ml) = water_with_sensor(
+            "test",
+            50.0,
+            1.0,
+            10.0,
+            &pump_sensor,
+            |secs| {
+                bursts.push(secs);
+                Ok(())
+            },
+            || {},
+        )
+        .unwrap();
+
+        assert_eq!(bursts, vec![10.0, 10.0]);
+        assert_eq!(pumped_secs, 20.0);
+        assert_eq!(pumped_ml, 20.0);
+    }
+
+    #[test]
+    fn stops_immediately_when_already_wet() {
+        let pump_sensor = PumpSensor {
+            sensor: Box::new(FakeSensor {
+                readings: vec![9.5],
+                next: Cell::new(0),
+            }),
+            dry_threshold: 3.0,
+            wet_threshold: 9.0,
+        };
+        let (pumped_secs, pumped_ml) = water_with_sensor(
+            "test",
+            30.0,
+            1.0,
+            10.0,
+            &pump_sensor,
+            |_| panic!("should not pump while already wet"),
+            || {},
+        )
+        .unwrap();
+        assert_eq!(pumped_secs, 0.0);
+        assert_eq!(pumped_ml, 0.0);
     }
 }
 impl fmt::Display for Pump {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "pump {} ({}), {:.1} mL/day at {:.1} mL/s on {}",
+            "pump {} ({}{}), {:.1} mL/day at {:.1} mL/s on {}",
             self.name,
             if self.pin.is_enabled() {
                 "enabled"
             } else {
                 "disabled"
             },
+            if self.failed { ", failed" } else { "" },
             self.ml_per_day,
             self.ml_per_s,
             self.pin
@@ -161,15 +367,195 @@ impl fmt::Display for Pump {
     }
 }
 
-fn run(pumps: &[Pump], watering_time: Time) -> Result<()> {
-    // Check date and time once per second.
-    let sleep_duration = Duration::from_millis(1_000);
+/// Record of the most recently completed watering cycle.
+#[derive(Clone, Debug)]
+struct LastRun {
+    at: OffsetDateTime,
+    ok: bool,
+}
+
+/// State shared between the scheduler loop in [`run`] and the optional HTTP
+/// status server, so both can observe and (for the server) trigger watering.
+struct Shared {
+    pumps: Mutex<Vec<Pump>>,
+    watering_time: Mutex<Time>,
+    pause_between_pumps: Mutex<Duration>,
+    time_jump_tolerance: Mutex<Duration>,
+    last_run: Mutex<Option<LastRun>>,
+    config_path: PathBuf,
+    config_mtime: Mutex<Option<SystemTime>>,
+    state_path: PathBuf,
+    state: Mutex<state::State>,
+}
+impl Shared {
+    /// Compute the next instant at which the daily watering is due.
+    fn next_watering(&self) -> OffsetDateTime {
+        let watering_time = *self.watering_time.lock().unwrap();
+        let t = OffsetDateTime::now_utc();
+        let mut watering_date_time = t.replace_time(watering_time);
+        if t >= watering_date_time {
+            watering_date_time += 1.days();
+        }
+        watering_date_time
+    }
+
+    /// Whether today's watering is due (we're past the daily start time) but
+    /// at least one pump hasn't completed it yet, e.g. because the process
+    /// was down at `daily_start_time`.
+    fn needs_catchup(&self, t: OffsetDateTime) -> bool {
+        let watering_time = *self.watering_time.lock().unwrap();
+        if t < t.replace_time(watering_time) {
+            return false;
+        }
+        let today = t.date().to_string();
+        // Acquire `pumps` before `state`, matching `water_all`'s lock order,
+        // to avoid a lock-order inversion between the scheduler and the HTTP
+        // server threads.
+        let pumps = self.pumps.lock().unwrap();
+        let state = self.state.lock().unwrap();
+        pumps.iter().filter(|pump| !pump.failed).any(|pump| {
+            state
+                .pumps
+                .get(&pump.name)
+                .map(|pump_state| pump_state.last_watered_date != today)
+                .unwrap_or(true)
+        })
+    }
+
+    /// Record that `pump_name` completed its watering step for `date`, and
+    /// persist the state file atomically so a crash mid-cycle resumes
+    /// cleanly.
+    fn record_watered(&self, pump_name: &str, date: String, secs: f64, ml: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.pumps.insert(
+            pump_name.to_string(),
+            state::PumpState {
+                last_watered_date: date,
+                last_secs: secs,
+                last_ml: ml,
+            },
+        );
+        if let Err(err) = state::save(&self.state_path, &state) {
+            error!("failed to persist watering state: {err:?}");
+        }
+    }
+
+    /// If `config_path`'s mtime changed since it was last read, re-parse it
+    /// and swap in the new pumps and watering time. Parse or GPIO errors are
+    /// logged and leave the previous good configuration in place.
+    fn reload_config_if_changed(&self) {
+        let mtime = match fs::metadata(&self.config_path).and_then(|metadata| metadata.modified()) {
+            Ok(mtime) => mtime,
+            Err(err) => {
+                warn!(
+                    "could not stat config file {}: {err}",
+                    self.config_path.display()
+                );
+                return;
+            }
+        };
+        {
+            let mut config_mtime = self.config_mtime.lock().unwrap();
+            if *config_mtime == Some(mtime) {
+                return;
+            }
+            *config_mtime = Some(mtime);
+        }
 
-    // Maximal error of a sleep duration before assuming a time jump.
-    let delta_t = Duration::from_millis(10_000);
+        let reload = || -> Result<(Vec<Pump>, Time, Duration, Duration)> {
+            let contents = fs::read_to_string(&self.config_path)?;
+            let config: config::Config = toml::from_str(&contents)?;
+            let config_time_format = format_description::parse("[hour]:[minute]:[second]")?;
+            let time_string = config.timing.daily_start_time.to_string();
+            let watering_time = Time::parse(&time_string, &config_time_format)?;
+            let mut pumps = self.pumps.lock().unwrap();
+            let new_pumps = build_pumps(
+                &config.pumps,
+                &mut pumps,
+                config.failures.max_consecutive_failures,
+            )?;
+            Ok((
+                new_pumps,
+                watering_time,
+                config.timing.pause_between_pumps,
+                config.timing.time_jump_tolerance,
+            ))
+        };
 
-    // Make a short pause between running successive pumps.
-    let pause_duration = Duration::from_millis(1_000);
+        match reload() {
+            Ok((new_pumps, watering_time, pause_between_pumps, time_jump_tolerance)) => {
+                *self.pumps.lock().unwrap() = new_pumps;
+                *self.watering_time.lock().unwrap() = watering_time;
+                *self.pause_between_pumps.lock().unwrap() = pause_between_pumps;
+                *self.time_jump_tolerance.lock().unwrap() = time_jump_tolerance;
+                info!(
+                    "reloaded configuration from {}",
+                    self.config_path.display()
+                );
+            }
+            Err(err) => {
+                error!(
+                    "failed to reload config file {}, keeping previous configuration: {err:?}",
+                    self.config_path.display()
+                );
+            }
+        }
+    }
+
+    /// Run a watering cycle over all pumps that haven't completed today yet
+    /// and record the result. Pumps already watered today are skipped, so an
+    /// interrupted cycle resumes only the pumps that didn't complete.
+    fn water_all(&self) {
+        let today = OffsetDateTime::now_utc().date().to_string();
+        let mut pumps = self.pumps.lock().unwrap();
+        let mut ok = true;
+        for pump in pumps.iter_mut() {
+            if pump.failed {
+                continue;
+            }
+
+            let already_done = self
+                .state
+                .lock()
+                .unwrap()
+                .pumps
+                .get(&pump.name)
+                .map(|pump_state| pump_state.last_watered_date == today)
+                .unwrap_or(false);
+            if already_done {
+                continue;
+            }
+
+            match pump.water() {
+                Ok((secs, ml)) => {
+                    pump.consecutive_failures = 0;
+                    self.record_watered(&pump.name, today.clone(), secs, ml);
+                }
+                Err(err) => {
+                    warn!("pumping failed with error {err:?}");
+                    ok = false;
+                    pump.consecutive_failures += 1;
+                    if pump.consecutive_failures >= pump.max_consecutive_failures {
+                        pump.failed = true;
+                        error!(
+                            "{}: disabled after {} consecutive failures",
+                            pump.name, pump.consecutive_failures
+                        );
+                    }
+                }
+            }
+            thread::sleep(*self.pause_between_pumps.lock().unwrap());
+        }
+        *self.last_run.lock().unwrap() = Some(LastRun {
+            at: OffsetDateTime::now_utc(),
+            ok,
+        });
+    }
+}
+
+fn run(shared: &Arc<Shared>) -> Result<()> {
+    // Check date and time once per second.
+    let sleep_duration = Duration::from_millis(1_000);
 
     let format = format_description::parse(
         "[year]-[month]-[day] \
@@ -179,19 +565,22 @@ fn run(pumps: &[Pump], watering_time: Time) -> Result<()> {
 
     'outer: loop {
         let mut t = OffsetDateTime::now_utc();
-        let mut watering_date_time = t.replace_time(watering_time);
-        if t >= watering_date_time {
-            watering_date_time += 1.days();
+        if shared.needs_catchup(t) {
+            info!("catching up on a missed watering cycle");
+            shared.water_all();
         }
+        let watering_date_time = shared.next_watering();
         info!("waiting for {}", watering_date_time.format(&format)?);
         while t < watering_date_time {
             thread::sleep(sleep_duration);
+            shared.reload_config_if_changed();
             let new_t = OffsetDateTime::now_utc();
 
             // Error of sleep duration.
             let e = new_t - (t + sleep_duration);
 
-            if e.abs() > delta_t {
+            let time_jump_tolerance = *shared.time_jump_tolerance.lock().unwrap();
+            if e.abs() > time_jump_tolerance {
                 info!("time jumped, restarting wait");
                 continue 'outer;
             }
@@ -202,12 +591,7 @@ fn run(pumps: &[Pump], watering_time: Time) -> Result<()> {
             "starting watering at {}",
             OffsetDateTime::now_utc().format(&format)?
         );
-        for pump in pumps {
-            if let Err(err) = pump.water() {
-                warn!("pumping failed with error {err:?}");
-            }
-            thread::sleep(pause_duration);
-        }
+        shared.water_all();
         info!(
             "finished watering at {}",
             OffsetDateTime::now_utc().format(&format)?
@@ -252,6 +636,16 @@ struct Args {
     ///
     /// Default is `water.log` in the current directory.
     log_file: Option<String>,
+    #[clap(long)]
+    /// Maximum size in bytes of the log file before it is rotated.
+    ///
+    /// Overrides `logging.max_log_bytes` in the config file.
+    max_log_bytes: Option<u64>,
+    #[clap(long)]
+    /// Number of rotated log backups to keep.
+    ///
+    /// Overrides `logging.log_backups` in the config file.
+    log_backups: Option<u32>,
     #[clap(short, long)]
     debug: bool,
     #[clap(subcommand)]
@@ -300,23 +694,109 @@ fn find_pin(name: &str, enable: bool) -> Result<Pin> {
     Ok(Pin::new(name, line, enable)?)
 }
 
+/// Build the configured pumps, reusing the GPIO line of any `previous` pump
+/// whose physical wiring (pin name and enabled flag) didn't change, so
+/// currently-held lines aren't needlessly dropped and re-requested on a
+/// config reload, even if the pump's logical name was also changed.
+fn build_pumps(
+    pump_configs: &BTreeMap<String, config::Pump>,
+    previous: &mut Vec<Pump>,
+    default_max_consecutive_failures: u32,
+) -> Result<Vec<Pump>> {
+    // For each config entry, in `pump_configs`' key order, find the index of
+    // a previous pump with matching physical wiring that hasn't already been
+    // claimed by an earlier entry. Two config entries that happen to share
+    // the same physical pin (e.g. a copy-pasted entry) must not both claim
+    // it. This only inspects `previous`, so it leaves `previous` untouched
+    // if a later, fallible step fails.
+    let mut claimed = vec![false; previous.len()];
+    let reuse_index: Vec<Option<usize>> = pump_configs
+        .values()
+        .map(|pump_config| {
+            let index = previous.iter().enumerate().position(|(i, pump)| {
+                !claimed[i]
+                    && pump.pin.name == pump_config.pin_name
+                    && pump.pin.is_enabled() == pump_config.enable
+            });
+            if let Some(i) = index {
+                claimed[i] = true;
+            }
+            index
+        })
+        .collect();
+
+    // Request GPIO lines for genuinely new or changed pins first. If this
+    // fails, `previous` hasn't been touched yet and the caller can keep it.
+    let mut new_pins = Vec::new();
+    for ((name, pump_config), reuse) in pump_configs.iter().zip(&reuse_index) {
+        if reuse.is_none() {
+            let pin = find_pin(&pump_config.pin_name, pump_config.enable)?;
+            new_pins.push((name.clone(), pin));
+        }
+    }
+
+    // Claim the reused previous pumps, highest index first, so removing one
+    // doesn't shift the not-yet-removed indices still needed by others.
+    // Anything left over in `previous` afterwards (pumps whose config was
+    // dropped entirely) is simply left for the caller to drop.
+    let mut reused_indices: Vec<usize> = reuse_index.iter().flatten().copied().collect();
+    reused_indices.sort_unstable_by(|a, b| b.cmp(a));
+    let mut reused: BTreeMap<usize, Pump> = BTreeMap::new();
+    for index in reused_indices {
+        reused.insert(index, previous.remove(index));
+    }
+
+    let mut pumps = Vec::new();
+    for ((name, pump_config), reuse) in pump_configs.iter().zip(&reuse_index) {
+        let pin = if let Some(index) = reuse {
+            reused
+                .remove(index)
+                .expect("reuse_index only points at claimed indices")
+                .pin
+        } else {
+            let index = new_pins.iter().position(|(n, _)| n == name).unwrap();
+            new_pins.remove(index).1
+        };
+        let sensor = pump_config.sensor.as_ref().map(|sensor_config| PumpSensor {
+            sensor: Box::new(FileSensor::new(&sensor_config.device)) as Box<dyn Sensor>,
+            dry_threshold: sensor_config.dry_threshold,
+            wet_threshold: sensor_config.wet_threshold,
+        });
+        let max_consecutive_failures = pump_config
+            .max_consecutive_failures
+            .unwrap_or(default_max_consecutive_failures);
+        let pump = Pump::new(
+            name,
+            pin,
+            pump_config.ml_per_s,
+            pump_config.ml_per_day,
+            sensor,
+            max_consecutive_failures,
+            pump_config.max_run_time,
+        )?;
+        info!("configured {pump}");
+        pumps.push(pump);
+    }
+    Ok(pumps)
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
     let config_file_name = args
         .config_file
         .unwrap_or(DEFAULT_CONFIG_FILE_NAME.to_string());
-    let mut file = File::open(config_file_name)?;
+    let config_path = PathBuf::from(&config_file_name);
+    let mut file = File::open(&config_path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
 
     let config: config::Config = toml::from_str(&contents)?;
 
     let log_file_name = args.log_file.unwrap_or(DEFAULT_LOG_FILE_NAME.to_string());
-    let log_file = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(log_file_name)?;
+    let max_log_bytes = args.max_log_bytes.unwrap_or(config.logging.max_log_bytes);
+    let log_backups = args.log_backups.unwrap_or(config.logging.log_backups);
+    let log_writer = RotatingWriter::new(log_file_name, max_log_bytes, log_backups)?;
     let log_config = ConfigBuilder::new()
         .set_time_format_str("%F %T%.3f")
         .set_thread_level(LevelFilter::Off)
@@ -326,7 +806,7 @@ fn main() -> Result<()> {
     } else {
         LevelFilter::Info
     };
-    let file_logger = WriteLogger::new(level_filter, log_config.clone(), log_file);
+    let file_logger = WriteLogger::new(level_filter, log_config.clone(), log_writer);
     if cfg!(feature = "term_logger") {
         let term_logger = TermLogger::new(
             LevelFilter::Debug,
@@ -345,16 +825,40 @@ fn main() -> Result<()> {
     let time_string = config.timing.daily_start_time.to_string();
     let watering_time = Time::parse(&time_string, &config_time_format)?;
 
-    let mut pumps = Vec::new();
-    for (name, pump_config) in config.pumps {
-        let pin = find_pin(&pump_config.pin_name, pump_config.enable)?;
-        let pump = Pump::new(&name, pin, pump_config.ml_per_s, pump_config.ml_per_day)?;
-        info!("configured {pump}");
-        pumps.push(pump);
-    }
+    let mut previous_pumps = Vec::new();
+    let pumps = build_pumps(
+        &config.pumps,
+        &mut previous_pumps,
+        config.failures.max_consecutive_failures,
+    )?;
+
+    let config_mtime = fs::metadata(&config_path).and_then(|metadata| metadata.modified()).ok();
+
+    let state_path = config_path.with_file_name(DEFAULT_STATE_FILE_NAME);
+    let state = state::load(&state_path).unwrap_or_else(|err| {
+        warn!("failed to load watering state from {}, starting fresh: {err:?}", state_path.display());
+        state::State::default()
+    });
 
     match args.command {
-        Command::Run => run(&pumps, watering_time),
+        Command::Run => {
+            let shared = Arc::new(Shared {
+                pumps: Mutex::new(pumps),
+                watering_time: Mutex::new(watering_time),
+                pause_between_pumps: Mutex::new(config.timing.pause_between_pumps),
+                time_jump_tolerance: Mutex::new(config.timing.time_jump_tolerance),
+                last_run: Mutex::new(None),
+                config_path,
+                config_mtime: Mutex::new(config_mtime),
+                state_path,
+                state: Mutex::new(state),
+            });
+            if let Some(http_config) = config.http {
+                let http_shared = Arc::clone(&shared);
+                thread::spawn(move || http::serve(http_config.bind_address, http_shared));
+            }
+            run(&shared)
+        }
         Command::Test(test_args) => test(&test_args, &pumps),
     }
 }
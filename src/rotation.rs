@@ -0,0 +1,86 @@
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// A [`Write`] implementation that caps a log file at a fixed byte budget.
+///
+/// Once the next write would exceed the budget, the current file is renamed
+/// to a numbered backup (shifting any existing backups up by one and
+/// dropping the oldest past `backups`) and a fresh file is opened.
+pub struct RotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    backups: u32,
+    file: File,
+    written: u64,
+}
+impl RotatingWriter {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        max_bytes: u64,
+        backups: u32,
+    ) -> io::Result<RotatingWriter> {
+        let path = path.into();
+        let file = OpenOptions::new().append(true).create(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingWriter {
+            path,
+            max_bytes,
+            backups,
+            file,
+            written,
+        })
+    }
+
+    fn backup_path(&self, n: u32) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.backups > 0 {
+            for n in (1..self.backups).rev() {
+                let from = self.backup_path(n);
+                if from.exists() {
+                    fs::rename(from, self.backup_path(n + 1))?;
+                }
+            }
+            if self.path.exists() {
+                fs::rename(&self.path, self.backup_path(1))?;
+            }
+            self.file = OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(&self.path)?;
+        } else {
+            // No backups kept: truncate the current file in place instead of
+            // just reopening it, since append mode wouldn't shrink it back
+            // under the byte budget.
+            self.file = OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .create(true)
+                .open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
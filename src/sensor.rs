@@ -0,0 +1,33 @@
+use std::fs;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+/// A moisture sensor that reports a raw measured value.
+///
+/// `Send` so `Box<dyn Sensor>` can live inside a `Pump` shared across the
+/// scheduler and HTTP server threads.
+pub trait Sensor: Send {
+    /// Read the current sensor value.
+    fn read(&self) -> Result<f64>;
+}
+
+/// A sensor backed by a 1-wire/ADC device file exposing a raw numeric value,
+/// e.g. a sysfs entry.
+pub struct FileSensor {
+    path: String,
+}
+impl FileSensor {
+    pub fn new(path: &str) -> FileSensor {
+        FileSensor {
+            path: path.to_string(),
+        }
+    }
+}
+impl Sensor for FileSensor {
+    fn read(&self) -> Result<f64> {
+        let contents = fs::read_to_string(&self.path)?;
+        let value = contents.trim().parse::<f64>()?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+type Error = Box<dyn std::error::Error>;
+type Result<T> = std::result::Result<T, Error>;
+
+/// Watering history, persisted across restarts so the scheduler can tell
+/// whether today's cycle already ran.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct State {
+    pub pumps: BTreeMap<String, PumpState>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PumpState {
+    /// Date (`YYYY-MM-DD`, UTC) the pump last completed its watering step.
+    pub last_watered_date: String,
+    /// Seconds actually pumped during that step.
+    pub last_secs: f64,
+    /// Water dispensed (mL) during that step.
+    pub last_ml: f64,
+}
+
+/// Load the state file, or an empty [`State`] if it doesn't exist yet.
+pub fn load(path: &Path) -> Result<State> {
+    if !path.exists() {
+        return Ok(State::default());
+    }
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Write the state file atomically: write to a temp file, then rename it
+/// into place, so a crash mid-write leaves the previous state intact.
+pub fn save(path: &Path, state: &State) -> Result<()> {
+    let contents = serde_json::to_string_pretty(state)?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}